@@ -21,14 +21,154 @@ use std::os::wasi::{
     io::{AsRawFd, FromRawFd, RawFd},
 };
 use std::{
+    collections::VecDeque,
     convert::TryInto,
-    ffi::{CStr, OsString},
+    ffi::{CStr, CString, OsString},
     fs, io,
     mem::MaybeUninit,
 };
 #[cfg(not(target_os = "wasi"))]
 use std::{ffi::OsStr, mem::ManuallyDrop};
 
+bitflags::bitflags! {
+    /// `RENAME_*` constants for use with [`renameat_with`].
+    pub struct RenameFlags: libc::c_uint {
+        /// `RENAME_EXCHANGE`
+        const EXCHANGE = 0x2;
+        /// `RENAME_NOREPLACE`
+        const NOREPLACE = 0x1;
+        /// `RENAME_WHITEOUT`
+        const WHITEOUT = 0x4;
+    }
+}
+
+bitflags::bitflags! {
+    /// `RESOLVE_*` constants for use with [`openat2`].
+    pub struct ResolveFlags: u64 {
+        /// `RESOLVE_NO_XDEV`
+        const NO_XDEV = 0x1;
+        /// `RESOLVE_NO_MAGICLINKS`
+        const NO_MAGICLINKS = 0x2;
+        /// `RESOLVE_NO_SYMLINKS`
+        const NO_SYMLINKS = 0x4;
+        /// `RESOLVE_BENEATH`
+        const BENEATH = 0x8;
+        /// `RESOLVE_IN_ROOT`
+        const IN_ROOT = 0x10;
+        /// `RESOLVE_CACHED`
+        const CACHED = 0x20;
+    }
+}
+
+bitflags::bitflags! {
+    /// `STATX_*` constants for use with [`statx`], selecting which fields
+    /// the kernel should try to populate.
+    pub struct StatxFlags: u32 {
+        /// `STATX_TYPE`
+        const TYPE = 0x1;
+        /// `STATX_MODE`
+        const MODE = 0x2;
+        /// `STATX_NLINK`
+        const NLINK = 0x4;
+        /// `STATX_UID`
+        const UID = 0x8;
+        /// `STATX_GID`
+        const GID = 0x10;
+        /// `STATX_ATIME`
+        const ATIME = 0x20;
+        /// `STATX_MTIME`
+        const MTIME = 0x40;
+        /// `STATX_CTIME`
+        const CTIME = 0x80;
+        /// `STATX_INO`
+        const INO = 0x100;
+        /// `STATX_SIZE`
+        const SIZE = 0x200;
+        /// `STATX_BLOCKS`
+        const BLOCKS = 0x400;
+        /// `STATX_BASIC_STATS`
+        const BASIC_STATS = 0x7ff;
+        /// `STATX_BTIME`
+        const BTIME = 0x800;
+        /// `STATX_ALL`
+        const ALL = 0xfff;
+    }
+}
+
+/// A timestamp as returned by [`statx`], in `struct statx_timestamp` layout.
+#[cfg(target_os = "linux")]
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct StatxTimestamp {
+    /// Seconds since the Epoch.
+    pub tv_sec: i64,
+    /// Nanoseconds since `tv_sec`.
+    pub tv_nsec: u32,
+    __reserved: i32,
+}
+
+/// Extended file metadata, as returned by [`statx`].
+///
+/// `stx_mask` records which of the other fields the kernel actually
+/// populated; callers should not trust a field the mask doesn't cover.
+#[cfg(target_os = "linux")]
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct Statx {
+    /// Which fields are valid, as a [`StatxFlags`]-shaped bitmask.
+    pub stx_mask: u32,
+    /// Preferred I/O block size.
+    pub stx_blksize: u32,
+    /// Additional file attribute bits (`STATX_ATTR_*`).
+    pub stx_attributes: u64,
+    /// Number of hard links.
+    pub stx_nlink: u32,
+    /// Owner user ID.
+    pub stx_uid: u32,
+    /// Owner group ID.
+    pub stx_gid: u32,
+    /// File type and mode.
+    pub stx_mode: u16,
+    __spare0: [u16; 1],
+    /// Inode number.
+    pub stx_ino: u64,
+    /// File size in bytes.
+    pub stx_size: u64,
+    /// Number of 512-byte blocks allocated.
+    pub stx_blocks: u64,
+    /// Mask of bits `stx_attributes` actually supports.
+    pub stx_attributes_mask: u64,
+    /// Last access time.
+    pub stx_atime: StatxTimestamp,
+    /// Creation ("birth") time.
+    pub stx_btime: StatxTimestamp,
+    /// Last status change time.
+    pub stx_ctime: StatxTimestamp,
+    /// Last modification time.
+    pub stx_mtime: StatxTimestamp,
+    /// Major ID, if this is a device file.
+    pub stx_rdev_major: u32,
+    /// Minor ID, if this is a device file.
+    pub stx_rdev_minor: u32,
+    /// Major ID of the device containing the filesystem.
+    pub stx_dev_major: u32,
+    /// Minor ID of the device containing the filesystem.
+    pub stx_dev_minor: u32,
+    /// Mount ID.
+    pub stx_mnt_id: u64,
+    __spare2: u64,
+    __spare3: [u64; 12],
+}
+
+/// The `struct open_how` argument to the Linux `openat2` syscall.
+#[cfg(target_os = "linux")]
+#[repr(C)]
+struct open_how {
+    flags: u64,
+    mode: u64,
+    resolve: u64,
+}
+
 /// Return a "file" which holds a handle which refers to the process current
 /// directory (`AT_FDCWD`). It is a `ManuallyDrop`, however the caller should
 /// not drop it explicitly, as it refers to an ambient authority rather than
@@ -63,6 +203,56 @@ unsafe fn _openat(dirfd: RawFd, path: &CStr, oflags: OFlags, mode: Mode) -> io::
     Ok(fs::File::from_raw_fd(fd as RawFd))
 }
 
+/// `openat2(dirfd, path, oflags, mode, resolve)`
+///
+/// This wraps the Linux `openat2` syscall, which resolves `path` relative to
+/// `dirfd` subject to the constraints in `resolve`, failing rather than
+/// silently escaping `dirfd` when a constraint like `RESOLVE_BENEATH` or
+/// `RESOLVE_IN_ROOT` would otherwise be violated.
+///
+/// <details>
+/// On `ENOSYS` (no `openat2` support) or `EINVAL` (an unrecognized field in
+/// `struct open_how`), callers should fall back to [`openat`].
+/// </details>
+#[cfg(target_os = "linux")]
+#[inline]
+pub fn openat2<P: PathArg, Fd: AsRawFd>(
+    dirfd: &Fd,
+    path: P,
+    oflags: OFlags,
+    mode: Mode,
+    resolve: ResolveFlags,
+) -> io::Result<fs::File> {
+    let dirfd = dirfd.as_raw_fd();
+    let path = path.as_cstr()?;
+    unsafe { _openat2(dirfd, &path, oflags, mode, resolve) }
+}
+
+#[cfg(target_os = "linux")]
+unsafe fn _openat2(
+    dirfd: RawFd,
+    path: &CStr,
+    oflags: OFlags,
+    mode: Mode,
+    resolve: ResolveFlags,
+) -> io::Result<fs::File> {
+    let how = open_how {
+        flags: oflags.bits() as u64,
+        mode: u64::from(mode.bits()),
+        resolve: resolve.bits(),
+    };
+
+    let fd = negone_err(libc::syscall(
+        libc::SYS_openat2,
+        dirfd as libc::c_int,
+        path.as_ptr(),
+        &how,
+        std::mem::size_of::<open_how>(),
+    ))?;
+
+    Ok(fs::File::from_raw_fd(fd as RawFd))
+}
+
 /// `readlinkat(fd, path)`
 #[inline]
 pub fn readlinkat<P: PathArg, Fd: AsRawFd>(dirfd: &Fd, path: P) -> io::Result<OsString> {
@@ -130,6 +320,113 @@ unsafe fn _mkdirat(dirfd: RawFd, path: &CStr, mode: Mode) -> io::Result<()> {
     ))
 }
 
+#[cfg(not(target_os = "wasi"))]
+bitflags::bitflags! {
+    /// `S_IF*` constants for use with [`mknodat`], identifying the kind of
+    /// special file to create.
+    pub struct FileType: libc::mode_t {
+        /// `S_IFIFO`
+        const IFIFO = libc::S_IFIFO;
+        /// `S_IFCHR`
+        const IFCHR = libc::S_IFCHR;
+        /// `S_IFBLK`
+        const IFBLK = libc::S_IFBLK;
+        /// `S_IFREG`
+        const IFREG = libc::S_IFREG;
+        /// `S_IFSOCK`
+        const IFSOCK = libc::S_IFSOCK;
+    }
+}
+
+/// `mknodat(dirfd, path, file_type | mode, dev)`
+#[cfg(not(target_os = "wasi"))]
+#[inline]
+pub fn mknodat<P: PathArg, Fd: AsRawFd>(
+    dirfd: &Fd,
+    path: P,
+    file_type: FileType,
+    mode: Mode,
+    dev: libc::dev_t,
+) -> io::Result<()> {
+    let dirfd = dirfd.as_raw_fd();
+    let path = path.as_cstr()?;
+    unsafe { _mknodat(dirfd, &path, file_type, mode, dev) }
+}
+
+#[cfg(not(target_os = "wasi"))]
+unsafe fn _mknodat(
+    dirfd: RawFd,
+    path: &CStr,
+    file_type: FileType,
+    mode: Mode,
+    dev: libc::dev_t,
+) -> io::Result<()> {
+    zero_ok(libc::mknodat(
+        dirfd as libc::c_int,
+        path.as_ptr(),
+        file_type.bits() | libc::mode_t::from(mode.bits()),
+        dev,
+    ))
+}
+
+/// `mknodat(dirfd, path, S_IFIFO | mode, 0)`
+#[cfg(not(target_os = "wasi"))]
+#[inline]
+pub fn mkfifoat<P: PathArg, Fd: AsRawFd>(dirfd: &Fd, path: P, mode: Mode) -> io::Result<()> {
+    mknodat(dirfd, path, FileType::IFIFO, mode, 0)
+}
+
+/// `makedev(major, minor)`, using the Linux glibc `dev_t` bit layout.
+#[cfg(not(target_os = "wasi"))]
+#[inline]
+pub fn makedev(major: u32, minor: u32) -> libc::dev_t {
+    let major = u64::from(major);
+    let minor = u64::from(minor);
+    let dev = (minor & 0xff)
+        | ((major & 0xfff) << 8)
+        | ((minor & !0xff) << 12)
+        | ((major & !0xfff) << 32);
+    dev as libc::dev_t
+}
+
+/// `major(dev)`, using the Linux glibc `dev_t` bit layout.
+#[cfg(not(target_os = "wasi"))]
+#[inline]
+pub fn major(dev: libc::dev_t) -> u32 {
+    let dev = dev as u64;
+    (((dev >> 8) & 0xfff) | ((dev >> 32) & 0xffff_f000)) as u32
+}
+
+/// `minor(dev)`, using the Linux glibc `dev_t` bit layout.
+#[cfg(not(target_os = "wasi"))]
+#[inline]
+pub fn minor(dev: libc::dev_t) -> u32 {
+    let dev = dev as u64;
+    ((dev & 0xff) | ((dev >> 12) & 0xffff_ff00)) as u32
+}
+
+#[cfg(test)]
+#[cfg(not(target_os = "wasi"))]
+mod dev_tests {
+    use super::{major, makedev, minor};
+
+    #[test]
+    fn makedev_roundtrips_major_and_minor() {
+        for (maj, min) in [(0, 0), (1, 1), (8, 1), (0xabc, 0x123), (0xfff, 0xffffff)] {
+            let dev = makedev(maj, min);
+            assert_eq!(major(dev), maj);
+            assert_eq!(minor(dev), min);
+        }
+    }
+
+    #[test]
+    fn makedev_roundtrips_high_bits() {
+        let dev = makedev(0xfffff, 0xfffff);
+        assert_eq!(major(dev), 0xfffff);
+        assert_eq!(minor(dev), 0xfffff);
+    }
+}
+
 /// `linkat(old_dirfd, old_path, new_dirfd, new_path, flags)`
 #[inline]
 pub fn linkat<P: PathArg, Q: PathArg, PFd: AsRawFd, QFd: AsRawFd>(
@@ -207,6 +504,62 @@ unsafe fn _renameat(
     ))
 }
 
+/// `renameat2(old_dirfd, old_path, new_dirfd, new_path, flags)`
+///
+/// `flags` is mutually exclusive between `RENAME_EXCHANGE` and
+/// `RENAME_NOREPLACE`.
+///
+/// <details>
+/// On platforms without a `renameat2` syscall, this falls back to plain
+/// `renameat` when `flags` is empty, and fails with `ENOSYS` otherwise.
+/// </details>
+#[inline]
+pub fn renameat_with<P: PathArg, Q: PathArg, PFd: AsRawFd, QFd: AsRawFd>(
+    old_dirfd: &PFd,
+    old_path: P,
+    new_dirfd: &QFd,
+    new_path: Q,
+    flags: RenameFlags,
+) -> io::Result<()> {
+    let old_dirfd = old_dirfd.as_raw_fd();
+    let new_dirfd = new_dirfd.as_raw_fd();
+    let old_path = old_path.as_cstr()?;
+    let new_path = new_path.as_cstr()?;
+    unsafe { _renameat2(old_dirfd, &old_path, new_dirfd, &new_path, flags) }
+}
+
+#[cfg(target_os = "linux")]
+unsafe fn _renameat2(
+    old_dirfd: RawFd,
+    old_path: &CStr,
+    new_dirfd: RawFd,
+    new_path: &CStr,
+    flags: RenameFlags,
+) -> io::Result<()> {
+    zero_ok(libc::syscall(
+        libc::SYS_renameat2,
+        old_dirfd as libc::c_int,
+        old_path.as_ptr(),
+        new_dirfd as libc::c_int,
+        new_path.as_ptr(),
+        flags.bits(),
+    ))
+}
+
+#[cfg(not(target_os = "linux"))]
+unsafe fn _renameat2(
+    old_dirfd: RawFd,
+    old_path: &CStr,
+    new_dirfd: RawFd,
+    new_path: &CStr,
+    flags: RenameFlags,
+) -> io::Result<()> {
+    if flags.is_empty() {
+        return _renameat(old_dirfd, old_path, new_dirfd, new_path);
+    }
+    Err(io::Error::from_raw_os_error(libc::ENOSYS))
+}
+
 /// `symlinkat(old_dirfd, old_path, new_dirfd, new_path)`
 #[inline]
 pub fn symlinkat<P: PathArg, Q: PathArg, Fd: AsRawFd>(
@@ -251,6 +604,39 @@ unsafe fn _statat(dirfd: RawFd, path: &CStr, flags: AtFlags) -> io::Result<LibcS
     Ok(stat.assume_init())
 }
 
+/// `statx(dirfd, path, flags, mask)`
+///
+/// <details>
+/// On `ENOSYS` (pre-4.11 kernels), callers should fall back to [`statat`],
+/// which cannot report `stx_btime` or a field-validity mask.
+/// </details>
+#[cfg(target_os = "linux")]
+#[inline]
+pub fn statx<P: PathArg, Fd: AsRawFd>(
+    dirfd: &Fd,
+    path: P,
+    flags: AtFlags,
+    mask: StatxFlags,
+) -> io::Result<Statx> {
+    let dirfd = dirfd.as_raw_fd();
+    let path = path.as_cstr()?;
+    unsafe { _statx(dirfd, &path, flags, mask) }
+}
+
+#[cfg(target_os = "linux")]
+unsafe fn _statx(dirfd: RawFd, path: &CStr, flags: AtFlags, mask: StatxFlags) -> io::Result<Statx> {
+    let mut statx = MaybeUninit::<Statx>::uninit();
+    zero_ok(libc::syscall(
+        libc::SYS_statx,
+        dirfd as libc::c_int,
+        path.as_ptr(),
+        flags.bits(),
+        mask.bits(),
+        statx.as_mut_ptr(),
+    ))?;
+    Ok(statx.assume_init())
+}
+
 /// `faccessat(dirfd, path, access, flags)`
 #[inline]
 pub fn accessat<P: PathArg, Fd: AsRawFd>(
@@ -349,6 +735,80 @@ unsafe fn _chmodat(dirfd: RawFd, path: &CStr, mode: Mode) -> io::Result<()> {
     ))
 }
 
+/// Build the `/proc/self/fd/<n>` path for `fd`.
+///
+/// A path built from an open fd's number always fits the `CStr` grammar, so
+/// this can't fail the way a caller-supplied path could.
+#[cfg(not(target_os = "wasi"))]
+fn proc_fd_path(fd: RawFd) -> CString {
+    CString::new(format!("/proc/self/fd/{}", fd)).unwrap()
+}
+
+/// `fchmodat(dirfd, path, mode, flags)`
+///
+/// <details>
+/// Linux's `fchmodat` syscall takes no flags argument, so when
+/// `AT_SYMLINK_NOFOLLOW` is requested this instead opens `path` as
+/// `O_PATH | O_NOFOLLOW` relative to `dirfd` and `chmod`s it through
+/// `/proc/self/fd/<n>`. This fails with `ENOTSUP` if `path` is itself a
+/// symlink, since a symlink has no mode of its own to change, or if the
+/// procfs fallback is genuinely unavailable.
+/// </details>
+#[cfg(not(target_os = "wasi"))]
+pub fn chmodat_with<P: PathArg, Fd: AsRawFd>(
+    dirfd: &Fd,
+    path: P,
+    mode: Mode,
+    flags: AtFlags,
+) -> io::Result<()> {
+    let dirfd = dirfd.as_raw_fd();
+    let path = path.as_cstr()?;
+    unsafe { _chmodat_with(dirfd, &path, mode, flags) }
+}
+
+#[cfg(target_os = "linux")]
+unsafe fn _chmodat_with(dirfd: RawFd, path: &CStr, mode: Mode, flags: AtFlags) -> io::Result<()> {
+    if !flags.contains(AtFlags::SYMLINK_NOFOLLOW) {
+        return _chmodat(dirfd, path, mode);
+    }
+
+    let handle = _openat(
+        dirfd,
+        path,
+        OFlags::PATH | OFlags::NOFOLLOW | OFlags::CLOEXEC,
+        Mode::empty(),
+    )?;
+
+    let stat = _statat(
+        handle.as_raw_fd(),
+        CStr::from_bytes_with_nul(b"\0").unwrap(),
+        AtFlags::EMPTY_PATH | AtFlags::SYMLINK_NOFOLLOW,
+    )?;
+    if stat.st_mode as libc::mode_t & libc::S_IFMT == libc::S_IFLNK {
+        return Err(io::Error::from_raw_os_error(libc::ENOTSUP));
+    }
+
+    let proc_path = proc_fd_path(handle.as_raw_fd());
+    zero_ok(libc::chmod(proc_path.as_ptr(), mode.bits())).map_err(|err| {
+        // `/proc` isn't mounted: the fallback itself is unavailable.
+        if err.raw_os_error() == Some(libc::ENOENT) {
+            io::Error::from_raw_os_error(libc::ENOTSUP)
+        } else {
+            err
+        }
+    })
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "wasi")))]
+unsafe fn _chmodat_with(dirfd: RawFd, path: &CStr, mode: Mode, flags: AtFlags) -> io::Result<()> {
+    zero_ok(libc::fchmodat(
+        dirfd as libc::c_int,
+        path.as_ptr(),
+        mode.bits(),
+        flags.bits(),
+    ))
+}
+
 /// `fclonefileat(src, dst_dir, dst, flags)`
 #[cfg(any(target_os = "macos", target_os = "ios"))]
 #[inline]
@@ -382,3 +842,179 @@ unsafe fn _fclonefileat(
 
     zero_ok(fclonefileat(srcfd, dst_dirfd, dst.as_ptr(), flags.bits()))
 }
+
+/// A directory handle that confines path resolution beneath itself.
+///
+/// This gives the same "never escape this directory" guarantee as
+/// `openat2`'s `RESOLVE_IN_ROOT`, for kernels that don't have `openat2`.
+/// [`Root::resolve`] walks the requested path one component at a time,
+/// expanding symlinks as it finds them and clamping `..` so it can never
+/// ascend above the directory the `Root` was opened on.
+#[cfg(not(target_os = "wasi"))]
+pub struct Root {
+    fd: fs::File,
+}
+
+/// The maximum number of symlink expansions [`Root::resolve`] will follow
+/// before giving up with `ELOOP`, mirroring the kernel's own `MAXSYMLINKS`.
+#[cfg(not(target_os = "wasi"))]
+const MAX_SYMLINK_EXPANSIONS: u32 = 40;
+
+#[cfg(not(target_os = "wasi"))]
+impl Root {
+    /// Open `path`, relative to `dirfd`, as a new `Root`.
+    pub fn new<P: PathArg, Fd: AsRawFd>(dirfd: &Fd, path: P) -> io::Result<Self> {
+        let fd = openat(
+            dirfd,
+            path,
+            OFlags::PATH | OFlags::NOFOLLOW | OFlags::CLOEXEC | OFlags::DIRECTORY,
+            Mode::empty(),
+        )?;
+        Ok(Self { fd })
+    }
+
+    /// Resolve `path` relative to this root, returning an `O_PATH` file
+    /// descriptor to the final component.
+    ///
+    /// The result is guaranteed not to have escaped the root: `..`
+    /// components are clamped at the root rather than rejected, and an
+    /// absolute symlink target is resolved relative to the root rather than
+    /// the real filesystem root.
+    pub fn resolve<P: PathArg>(&self, path: P) -> io::Result<fs::File> {
+        let path = path.as_cstr()?;
+        let mut components = Self::split_components(OsStr::from_bytes(path.to_bytes()));
+
+        let mut ancestors: Vec<fs::File> = Vec::new();
+        let mut current = self.reopen()?;
+        let mut expansions = 0_u32;
+
+        while let Some(component) = components.pop_front() {
+            if component == ".." {
+                // ".." at the root is a no-op; we never pop past it.
+                if let Some(parent) = ancestors.pop() {
+                    current = parent;
+                }
+                continue;
+            }
+
+            let next = openat(
+                &current,
+                component.as_os_str(),
+                OFlags::PATH | OFlags::NOFOLLOW | OFlags::CLOEXEC,
+                Mode::empty(),
+            )?;
+
+            let stat = statat(&next, "", AtFlags::EMPTY_PATH | AtFlags::SYMLINK_NOFOLLOW)?;
+            if stat.st_mode as libc::mode_t & libc::S_IFMT == libc::S_IFLNK {
+                expansions += 1;
+                if expansions > MAX_SYMLINK_EXPANSIONS {
+                    return Err(io::Error::from_raw_os_error(libc::ELOOP));
+                }
+
+                // Read the link through `next`'s own fd rather than by name
+                // through `current`, so we can't be raced: this is
+                // provably the same symlink `statat` just inspected.
+                let target = Self::readlink_self(&next)?;
+                let is_absolute = target.as_bytes().first() == Some(&b'/');
+
+                let mut target_components = Self::split_components(&target);
+                target_components.extend(components);
+                components = target_components;
+
+                if is_absolute {
+                    // Restart from the root rather than the filesystem `/`.
+                    ancestors.clear();
+                    current = self.reopen()?;
+                }
+                continue;
+            }
+
+            ancestors.push(current);
+            current = next;
+        }
+
+        Ok(current)
+    }
+
+    fn reopen(&self) -> io::Result<fs::File> {
+        self.fd.try_clone()
+    }
+
+    /// Read the target of the symlink `handle` itself refers to, via
+    /// `/proc/self/fd/<n>`, so the read can't be raced against whatever
+    /// `handle`'s path used to name.
+    fn readlink_self(handle: &fs::File) -> io::Result<OsString> {
+        let proc_path = proc_fd_path(handle.as_raw_fd());
+        unsafe { _readlinkat(libc::AT_FDCWD, &proc_path) }
+    }
+
+    fn split_components(path: &OsStr) -> VecDeque<OsString> {
+        path.as_bytes()
+            .split(|&b| b == b'/')
+            .filter(|component| !component.is_empty() && *component != b".")
+            .map(|component| OsStr::from_bytes(component).to_os_string())
+            .collect()
+    }
+}
+
+#[cfg(not(target_os = "wasi"))]
+impl AsRawFd for Root {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+#[cfg(test)]
+#[cfg(target_os = "linux")]
+mod root_tests {
+    use super::*;
+    use std::os::unix::fs::{symlink, MetadataExt};
+
+    fn same_file(fd: &fs::File, path: &std::path::Path) -> bool {
+        let expected = fs::metadata(path).unwrap();
+        let mut stat = MaybeUninit::<libc::stat>::uninit();
+        let rc = unsafe { libc::fstat(fd.as_raw_fd(), stat.as_mut_ptr()) };
+        assert_eq!(rc, 0);
+        let stat = unsafe { stat.assume_init() };
+        stat.st_dev == expected.dev() && stat.st_ino == expected.ino()
+    }
+
+    #[test]
+    fn resolve_clamps_dotdot_at_root() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("in")).unwrap();
+        fs::write(dir.path().join("in/marker"), b"inside").unwrap();
+        symlink("../../../../../../in/marker", dir.path().join("escape")).unwrap();
+
+        let root = Root::new(&*cwd(), dir.path()).unwrap();
+        let resolved = root.resolve("escape").unwrap();
+
+        assert!(same_file(&resolved, &dir.path().join("in/marker")));
+    }
+
+    #[test]
+    fn resolve_reanchors_absolute_symlinks_at_root() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("in")).unwrap();
+        fs::write(dir.path().join("in/marker"), b"inside").unwrap();
+        symlink("/in/marker", dir.path().join("abs")).unwrap();
+
+        let root = Root::new(&*cwd(), dir.path()).unwrap();
+        let resolved = root.resolve("abs").unwrap();
+
+        assert!(same_file(&resolved, &dir.path().join("in/marker")));
+    }
+
+    #[test]
+    fn resolve_fails_on_symlink_loop() {
+        let dir = tempfile::tempdir().unwrap();
+        for i in 0..45 {
+            symlink(format!("link{}", i + 1), dir.path().join(format!("link{}", i))).unwrap();
+        }
+        symlink("link0", dir.path().join("link45")).unwrap();
+
+        let root = Root::new(&*cwd(), dir.path()).unwrap();
+        let err = root.resolve("link0").unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::ELOOP));
+    }
+}